@@ -0,0 +1,60 @@
+#![feature(test)]
+
+extern crate test;
+extern crate parity_wasm;
+extern crate wasm_utils;
+
+use test::Bencher;
+use parity_wasm::{builder, elements};
+use wasm_utils::CREATE_SYMBOL;
+use wasm_utils::pack::pack_instance;
+
+fn ctor_fixture() -> elements::Module {
+    builder::module()
+        .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(256 as u32, None)
+        .build()
+        .data()
+            .offset(elements::Opcode::I32Const(16))
+            .value(vec![0u8])
+        .build()
+        .function()
+            .signature().param().i32().build()
+            .body()
+                .with_opcodes(elements::Opcodes::new(vec![elements::Opcode::End]))
+                .build()
+        .build()
+        .export()
+            .field(CREATE_SYMBOL)
+            .internal().func(0)
+        .build()
+    .build()
+}
+
+/// Packing an owned `Vec<u8>` takes the zero-copy path: `raw_module` is moved straight into the
+/// `DataSegment`, with no extra clone taken on top of the one `b.iter` makes to hand each
+/// iteration its own owned buffer. Compare against `pack_instance_large_contract_borrowed`
+/// below, which pays for an internal copy on every iteration, to see the saving.
+#[bench]
+fn pack_instance_large_contract(b: &mut Bencher) {
+    let raw_module = vec![0u8; 4 * 1024 * 1024];
+
+    b.iter(|| {
+        pack_instance(raw_module.clone(), ctor_fixture()).expect("packing to succeed")
+    });
+}
+
+/// Baseline for the benchmark above: passing a borrowed `&[u8]` forces `pack_instance` to take
+/// the `Cow::into_owned()` branch and copy the whole contract code once per call, which is the
+/// cost every caller used to pay before `pack_instance` went generic over `Into<Cow<[u8]>>`.
+#[bench]
+fn pack_instance_large_contract_borrowed(b: &mut Bencher) {
+    let raw_module = vec![0u8; 4 * 1024 * 1024];
+
+    b.iter(|| {
+        pack_instance(&raw_module[..], ctor_fixture()).expect("packing to succeed")
+    });
+}
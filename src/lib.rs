@@ -0,0 +1,9 @@
+extern crate parity_wasm;
+
+pub mod pack;
+pub mod cap_storage;
+
+/// Export name the packer expects the constructor body to live under before packing.
+pub const CREATE_SYMBOL: &'static str = "_create";
+/// Export name the packed contract's runtime entry point is renamed to.
+pub const CALL_SYMBOL: &'static str = "_call";
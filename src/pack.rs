@@ -1,7 +1,31 @@
-use parity_wasm::elements::{self, Section, Opcode, DataSegment, InitExpr, Internal};
+use std::borrow::Cow;
+use parity_wasm::elements::{self, Section, Opcode, DataSegment, InitExpr, Internal, External};
 use parity_wasm::builder;
 use super::{CREATE_SYMBOL, CALL_SYMBOL};
 
+/// Size of a linear memory page, as defined by the wasm spec.
+const WASM_PAGE_SIZE: u32 = 65536;
+
+/// Selects the width of the code address/length fields the packed wrapper writes into the
+/// descriptor pointer: 4-byte fields and `I32Store` for a wasm32 target, 8-byte fields and
+/// `I64Store` for a wasm64 one. Mirrors the `AddressWidth` the `#[contract_call]` derive macro
+/// uses on the decoding side, so both ends agree on the descriptor layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressWidth {
+    Wasm32,
+    Wasm64,
+}
+
+impl AddressWidth {
+    /// Size in bytes of a single descriptor field under this address width.
+    fn field_size(&self) -> i32 {
+        match *self {
+            AddressWidth::Wasm32 => 4,
+            AddressWidth::Wasm64 => 8,
+        }
+    }
+}
+
 /// Pack error.
 ///
 /// Pack has number of assumptions of passed module structure.
@@ -17,96 +41,172 @@ pub enum Error {
     InvalidCreateMember,
 }
 
+/// Returns the parameter layout the exported "_create" function of `module` accepts, so
+/// callers can know what to pass at deploy time without hand-decoding the type section.
+///
+/// The last parameter is always the descriptor pointer (`i32`) the packed wrapper stores the
+/// code address/length into; any parameters before it are deployment arguments forwarded
+/// through to "_create" unchanged.
+pub fn create_params(module: &elements::Module) -> Result<Vec<elements::ValueType>, Error> {
+    Ok(create_function(module)?.1)
+}
+
+/// Locates the exported "_create" function and validates its signature, returning its index
+/// within the function section along with its full parameter list (deploy arguments followed
+/// by the trailing descriptor pointer).
+fn create_function(module: &elements::Module) -> Result<(usize, Vec<elements::ValueType>), Error> {
+    let ctor_import_functions = module.import_section().map(|x| x.functions()).unwrap_or(0);
+
+    let found_entry = module.export_section().ok_or(Error::NoExportSection)?.entries().iter()
+        .find(|entry| CREATE_SYMBOL == entry.field()).ok_or(Error::NoCreateSymbol)?;
+
+    let function_index: usize = match found_entry.internal() {
+        &Internal::Function(index) => index as usize,
+        _ => { return Err(Error::InvalidCreateMember) },
+    };
+
+    let type_id = module.function_section().ok_or(Error::NoCodeSection)?
+        .entries().get(function_index).ok_or(Error::MalformedModule)?
+        .type_ref();
+
+    let params = match module.type_section().ok_or(Error::NoTypeSection)?
+        .types().get(type_id as usize).ok_or(Error::MalformedModule)?
+    {
+        &elements::Type::Function(ref f) => {
+            // Constructor must take at least the trailing descriptor pointer, and nothing but
+            // `i32` in that last position; any leading params are forwarded deploy arguments.
+            if f.params().is_empty() || f.params()[f.params().len() - 1] != elements::ValueType::I32 {
+                return Err(Error::InvalidCreateSignature);
+            }
+            if f.return_type().is_some() {
+                return Err(Error::InvalidCreateSignature);
+            }
+            f.params().to_vec()
+        }
+    };
+
+    // Calculates a function index within module's function section
+    Ok((function_index - ctor_import_functions, params))
+}
+
 /// If module has an exported "_create" function we want to pack it into "constructor".
 /// `raw_module` is the actual contract code
 /// `ctor_module` is the constructor which should return `raw_module`
-pub fn pack_instance(raw_module: Vec<u8>, mut ctor_module: elements::Module) -> Result<elements::Module, Error> {
+///
+/// "_create" may take leading deployment arguments ahead of its mandatory trailing `i32`
+/// descriptor pointer, e.g. `func(i32, i64, i32)`; see [`create_params`].
+///
+/// `raw_module` is accepted as anything convertible into `Cow<[u8]>`, so a caller already
+/// holding an owned `Vec<u8>` (the common case for large contract code) hands it straight
+/// through to the single `DataSegment` with no intermediate clone; only a borrowed `&[u8]`
+/// pays for one unavoidable copy.
+///
+/// Targets a wasm32 address space; see [`pack_instance_with_width`] for wasm64.
+pub fn pack_instance<'a, T: Into<Cow<'a, [u8]>>>(raw_module: T, ctor_module: elements::Module) -> Result<elements::Module, Error> {
+    pack_instance_with_width(raw_module, ctor_module, AddressWidth::Wasm32)
+}
+
+/// As [`pack_instance`], but writes the code address/length descriptor fields at `width`
+/// instead of always assuming a 32-bit address space.
+pub fn pack_instance_with_width<'a, T: Into<Cow<'a, [u8]>>>(
+    raw_module: T,
+    mut ctor_module: elements::Module,
+    width: AddressWidth,
+) -> Result<elements::Module, Error> {
+    let raw_module: Cow<[u8]> = raw_module.into();
+    let raw_len = raw_module.len();
 
     // Total number of constructor module import functions
     let ctor_import_functions = ctor_module.import_section().map(|x| x.functions()).unwrap_or(0);
 
     // We need to find an internal ID of function witch is exported as "_create"
     // in order to find it in the Code section of the module
-    let create_func_id = {
-        let found_entry = ctor_module.export_section().ok_or(Error::NoExportSection)?.entries().iter()
-            .find(|entry| CREATE_SYMBOL == entry.field()).ok_or(Error::NoCreateSymbol)?;
-
-        let function_index: usize = match found_entry.internal() {
-            &Internal::Function(index) => index as usize,
-            _ => { return Err(Error::InvalidCreateMember) },
-        };
-
-        // Constructor should be of signature `func(i32)` (void), fail otherwise
-        let type_id = ctor_module.function_section().ok_or(Error::NoCodeSection)?
-            .entries().get(function_index).ok_or(Error::MalformedModule)?
-            .type_ref();
-
-        match ctor_module.type_section().ok_or(Error::NoTypeSection)?
-            .types().get(type_id as usize).ok_or(Error::MalformedModule)?
-        {
-            &elements::Type::Function(ref f) => {
-                if f.params().len() != 1 || f.params()[0] != elements::ValueType::I32 {
-                    return Err(Error::InvalidCreateSignature);
-                }
-                if f.return_type().is_some() {
-                    return Err(Error::InvalidCreateSignature);
-                }
-            }
-        };
-
-        // Calculates a function index within module's function section
-        function_index - ctor_import_functions
-    };
+    let (create_func_id, create_params) = create_function(&ctor_module)?;
+    let descriptor_local = (create_params.len() - 1) as u32;
 
     // If new function is put in ctor module, it will have this callable index
     let last_function_index = ctor_module.function_section().map(|x| x.entries().len()).unwrap_or(0)
         + ctor_import_functions;
 
-    // Code data address is an address where we put the contract's code (raw_module)
-    let mut code_data_address = 0i32;
+    // Code data address is an address where we put the contract's code (raw_module). There is
+    // ever only one DataSegment built below, so `raw_module` is moved into it exactly once:
+    // zero-copy if the caller already owned a `Vec<u8>`, one copy if they only lent us a slice.
+    let code_data_address;
 
-    for section in ctor_module.sections_mut() {
-        match section {
-            // TODO: add data section is there no one
-            &mut Section::Data(ref mut data_section) => {
-                let (index, offset) = if let Some(ref entry) = data_section.entries().iter().last() {
-                    if let Opcode::I32Const(offst) = entry.offset().code()[0] {
-                        let len = entry.value().len() as i32;
-                        let offst = offst as i32;
-                        (entry.index(), offst + (len + 4) - len % 4)
-                    } else {
-                        (0, 0)
-                    }
-                } else {
-                    (0, 0)
-                };
-                let code_data = DataSegment::new(
-                    index,
-                    InitExpr::new(vec![Opcode::I32Const(offset), Opcode::End]),
-                    raw_module.clone()
-                );
-                data_section.entries_mut().push(code_data);
-                code_data_address = offset;
+    if let Some((index, offset)) = ctor_module.data_section().map(|data_section| {
+        data_section.entries().iter().last().and_then(|entry| match entry.offset().code()[0] {
+            Opcode::I32Const(offst) => {
+                let len = entry.value().len() as i32;
+                Some((entry.index(), offst + (len + 4) - len % 4))
             },
-            _ => {;}
-        }
+            _ => None,
+        }).unwrap_or((0, 0))
+    }) {
+        let code_data = DataSegment::new(
+            index,
+            InitExpr::new(vec![Opcode::I32Const(offset), Opcode::End]),
+            raw_module.into_owned(),
+        );
+        ctor_module.data_section_mut().expect("data_section() returned Some above; qed")
+            .entries_mut().push(code_data);
+        code_data_address = offset;
+    } else {
+        // Constructors with no data section (the common case for minimal ctors) would
+        // otherwise leave `code_data_address` at 0 with nothing ever written.
+        ensure_memory_capacity(&mut ctor_module, raw_len);
+
+        let code_data = DataSegment::new(
+            0,
+            InitExpr::new(vec![Opcode::I32Const(0), Opcode::End]),
+            raw_module.into_owned(),
+        );
+        ctor_module.sections_mut().push(
+            Section::Data(elements::DataSection::with_entries(vec![code_data]))
+        );
+        code_data_address = 0;
     }
 
-    let mut new_module = builder::from_module(ctor_module)
-        .function()
-        .signature().param().i32().build()
-        .body().with_opcodes(elements::Opcodes::new(
-            vec![
-                Opcode::GetLocal(0),
-                Opcode::Call(create_func_id as u32),
-                Opcode::GetLocal(0),
-                Opcode::I32Const(code_data_address),
-                Opcode::I32Store(0, 8),
-                Opcode::GetLocal(0),
-                Opcode::I32Const(raw_module.len() as i32),
-                Opcode::I32Store(0, 12),
-                Opcode::End,
-            ])).build()
+    // Forward every leading deploy argument to "_create" unchanged, then store the packed
+    // code address/length at the trailing descriptor pointer, same as the fixed-arity case.
+    let mut wrapper_opcodes = Vec::with_capacity(create_params.len() + 8);
+    for local in 0..create_params.len() as u32 {
+        wrapper_opcodes.push(Opcode::GetLocal(local));
+    }
+    wrapper_opcodes.push(Opcode::Call(create_func_id as u32));
+
+    // The descriptor holds [context_ptr, context_len, result_ptr, result_len], each
+    // `field_size` bytes wide; we only ever fill in the trailing result_ptr/result_len pair.
+    let field_size = width.field_size();
+    let result_ptr_offset = (2 * field_size) as u32;
+    let result_len_offset = (3 * field_size) as u32;
+
+    match width {
+        AddressWidth::Wasm32 => {
+            wrapper_opcodes.push(Opcode::GetLocal(descriptor_local));
+            wrapper_opcodes.push(Opcode::I32Const(code_data_address));
+            wrapper_opcodes.push(Opcode::I32Store(0, result_ptr_offset));
+            wrapper_opcodes.push(Opcode::GetLocal(descriptor_local));
+            wrapper_opcodes.push(Opcode::I32Const(raw_len as i32));
+            wrapper_opcodes.push(Opcode::I32Store(0, result_len_offset));
+        },
+        AddressWidth::Wasm64 => {
+            wrapper_opcodes.push(Opcode::GetLocal(descriptor_local));
+            wrapper_opcodes.push(Opcode::I64Const(code_data_address as i64));
+            wrapper_opcodes.push(Opcode::I64Store(0, result_ptr_offset));
+            wrapper_opcodes.push(Opcode::GetLocal(descriptor_local));
+            wrapper_opcodes.push(Opcode::I64Const(raw_len as i64));
+            wrapper_opcodes.push(Opcode::I64Store(0, result_len_offset));
+        },
+    }
+    wrapper_opcodes.push(Opcode::End);
+
+    let mut wrapper_signature = builder::from_module(ctor_module).function().signature();
+    for param in &create_params {
+        wrapper_signature = wrapper_signature.with_param(*param);
+    }
+
+    let mut new_module = wrapper_signature.build()
+        .body().with_opcodes(elements::Opcodes::new(wrapper_opcodes)).build()
             .build()
         .build();
 
@@ -128,6 +228,50 @@ pub fn pack_instance(raw_module: Vec<u8>, mut ctor_module: elements::Module) ->
     Ok(new_module)
 }
 
+/// Sizes the internal linear memory of `module` to hold `data_len` bytes at offset 0: grows the
+/// first entry of an existing `Memory` section, or adds a `Memory` section if there is none.
+/// Growth stops at a declared `maximum`, so the result can still be smaller than `data_len`.
+/// A module with an imported memory is returned unchanged, whatever that memory's size.
+fn ensure_memory_capacity(module: &mut elements::Module, data_len: usize) {
+    let required_pages = (data_len as u32 + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+
+    let has_imported_memory = module.import_section()
+        .map(|section| section.entries().iter().any(|entry| match entry.external() {
+            &External::Memory(_) => true,
+            _ => false,
+        }))
+        .unwrap_or(false);
+
+    // An imported memory is owned by the host; we only ever grow an internal one.
+    if has_imported_memory {
+        return;
+    }
+
+    let mut has_memory_section = false;
+    for section in module.sections_mut() {
+        if let &mut Section::Memory(ref mut memory_section) = section {
+            has_memory_section = true;
+            if let Some(entry) = memory_section.entries_mut().get_mut(0) {
+                if entry.limits().initial() < required_pages {
+                    // Never grow `initial` past a declared `maximum`; that would make the
+                    // module invalid rather than just short on pages.
+                    let new_initial = match entry.limits().maximum() {
+                        Some(max) if required_pages > max => max,
+                        _ => required_pages,
+                    };
+                    *entry = elements::MemoryType::new(new_initial, entry.limits().maximum());
+                }
+            }
+        }
+    }
+
+    if !has_memory_section {
+        module.sections_mut().push(Section::Memory(
+            elements::MemorySection::with_entries(vec![elements::MemoryType::new(required_pages, None)])
+        ));
+    }
+}
+
 #[cfg(test)]
 mod test {
     extern crate parity_wasm;
@@ -215,4 +359,296 @@ mod test {
 
         contract_module_instance.execute_export(CALL_SYMBOL, execution_params).expect("Constructed contract failed to execute");
     }
+
+    #[test]
+    fn call_returns_code_without_data_section() {
+        let mut module = builder::module()
+            .import()
+                .module("env")
+                .field("memory")
+                .external()
+                .memory(1 as u32, Some(1 as u32))
+            .build()
+            .function()
+                .signature().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(
+                        vec![
+                            elements::Opcode::End
+                        ]
+                    ))
+                    .build()
+            .build()
+            .function()
+                .signature().param().i32().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(
+                        vec![
+                            elements::Opcode::End
+                        ]
+                    ))
+                    .build()
+            .build()
+            .export()
+                .field(CALL_SYMBOL)
+                .internal().func(0)
+            .build()
+            .export()
+                .field(CREATE_SYMBOL)
+                .internal().func(1)
+            .build()
+        .build();
+
+        // This ctor has no initial data section at all, unlike `call_returns_code` above.
+        let mut ctor_module = module.clone();
+        optimize(&mut module, vec![CALL_SYMBOL]).expect("Optimizer to finish without errors");
+        optimize(&mut ctor_module, vec![CREATE_SYMBOL]).expect("Optimizer to finish without errors");
+
+        assert!(ctor_module.data_section().is_none(), "ctor fixture must start without a data section");
+
+        let raw_module = parity_wasm::serialize(module).unwrap();
+        let ctor_module = pack_instance(raw_module.clone(), ctor_module).expect("Packing failed");
+
+        let program = parity_wasm::DefaultProgramInstance::new().expect("Program instance failed to load");
+        let env_instance = program.module("env").expect("Wasm program to contain env module");
+        let env_memory = env_instance.memory(interpreter::ItemIndex::Internal(0)).expect("Linear memory to exist in wasm runtime");
+
+        let execution_params = interpreter::ExecutionParams::default();
+        let constructor_module = program.add_module("contract", ctor_module, None).expect("Failed to initialize module");
+
+        let _ = constructor_module.execute_export(CALL_SYMBOL, execution_params.add_argument(RuntimeValue::I32(1024)));
+
+        let pointer = LittleEndian::read_u32(&env_memory.get(1024 + 8, 4).unwrap());
+        let len = LittleEndian::read_u32(&env_memory.get(1024 + 12, 4).unwrap());
+
+        let contract_code = env_memory.get(pointer, len as usize).expect("Failed to get code");
+
+        assert_eq!(raw_module, contract_code);
+    }
+
+    #[test]
+    fn ensure_memory_capacity_adds_section_when_absent() {
+        // No memory import and no data section at all, unlike every fixture above: this is the
+        // case `ensure_memory_capacity` exists for, where `pack_instance` must add a fresh
+        // internal Memory section rather than relying on one the ctor already declared.
+        let mut ctor_module = builder::module()
+            .function()
+                .signature().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(vec![elements::Opcode::End]))
+                    .build()
+            .build()
+            .function()
+                .signature().param().i32().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(vec![elements::Opcode::End]))
+                    .build()
+            .build()
+            .export()
+                .field(CREATE_SYMBOL)
+                .internal().func(1)
+            .build()
+        .build();
+        optimize(&mut ctor_module, vec![CREATE_SYMBOL]).expect("Optimizer to finish without errors");
+
+        assert!(ctor_module.import_section().is_none(), "ctor fixture must start without any imports");
+        assert!(ctor_module.memory_section().is_none(), "ctor fixture must start without any memory");
+
+        // Bigger than a single wasm page, so pack_instance must grow past the 1-page default.
+        let raw_module = vec![0u8; WASM_PAGE_SIZE as usize + 1024];
+        let packed = pack_instance(raw_module, ctor_module).expect("Packing failed");
+
+        let memory_section = packed.memory_section()
+            .expect("pack_instance must add a Memory section when none existed");
+        assert_eq!(
+            memory_section.entries()[0].limits().initial(), 2,
+            "code spans just past one page, so the added section must be sized to 2 pages"
+        );
+    }
+
+    #[test]
+    fn ensure_memory_capacity_never_exceeds_declared_maximum() {
+        // The ctor already declares an internal memory with a hard maximum of 1 page; forcing
+        // growth past that must clamp to the maximum rather than emit `initial > maximum`.
+        let mut ctor_module = builder::module()
+            .function()
+                .signature().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(vec![elements::Opcode::End]))
+                    .build()
+            .build()
+            .function()
+                .signature().param().i32().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(vec![elements::Opcode::End]))
+                    .build()
+            .build()
+            .export()
+                .field(CREATE_SYMBOL)
+                .internal().func(1)
+            .build()
+        .build();
+        ctor_module.sections_mut().push(Section::Memory(
+            elements::MemorySection::with_entries(vec![elements::MemoryType::new(1, Some(1))])
+        ));
+        optimize(&mut ctor_module, vec![CREATE_SYMBOL]).expect("Optimizer to finish without errors");
+
+        let raw_module = vec![0u8; WASM_PAGE_SIZE as usize + 1024];
+        let packed = pack_instance(raw_module, ctor_module).expect("Packing failed");
+
+        let memory_section = packed.memory_section().expect("memory section must survive packing");
+        assert_eq!(
+            memory_section.entries()[0].limits().initial(), 1,
+            "growth must clamp at the declared maximum instead of producing initial > maximum"
+        );
+        assert_eq!(memory_section.entries()[0].limits().maximum(), Some(1));
+    }
+
+    #[test]
+    fn call_returns_code_with_extra_ctor_args() {
+        let mut module = builder::module()
+            .import()
+                .module("env")
+                .field("memory")
+                .external()
+                .memory(1 as u32, Some(1 as u32))
+            .build()
+            .data()
+                .offset(elements::Opcode::I32Const(16))
+                .value(vec![0u8])
+            .build()
+            .function()
+                .signature().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(
+                        vec![
+                            elements::Opcode::End
+                        ]
+                    ))
+                    .build()
+            .build()
+            .function()
+                // ctor takes a deploy argument (local 0) ahead of the mandatory descriptor
+                // pointer (local 1)
+                .signature().param().i32().param().i32().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(
+                        vec![
+                            elements::Opcode::End
+                        ]
+                    ))
+                    .build()
+            .build()
+            .export()
+                .field(CALL_SYMBOL)
+                .internal().func(0)
+            .build()
+            .export()
+                .field(CREATE_SYMBOL)
+                .internal().func(1)
+            .build()
+        .build();
+
+        let mut ctor_module = module.clone();
+        optimize(&mut module, vec![CALL_SYMBOL]).expect("Optimizer to finish without errors");
+        optimize(&mut ctor_module, vec![CREATE_SYMBOL]).expect("Optimizer to finish without errors");
+
+        assert_eq!(
+            create_params(&ctor_module).expect("_create to be found"),
+            vec![elements::ValueType::I32, elements::ValueType::I32]
+        );
+
+        let raw_module = parity_wasm::serialize(module).unwrap();
+        let ctor_module = pack_instance(raw_module.clone(), ctor_module).expect("Packing failed");
+
+        let program = parity_wasm::DefaultProgramInstance::new().expect("Program instance failed to load");
+        let env_instance = program.module("env").expect("Wasm program to contain env module");
+        let env_memory = env_instance.memory(interpreter::ItemIndex::Internal(0)).expect("Linear memory to exist in wasm runtime");
+
+        let execution_params = interpreter::ExecutionParams::default();
+        let constructor_module = program.add_module("contract", ctor_module, None).expect("Failed to initialize module");
+
+        let _ = constructor_module.execute_export(
+            CALL_SYMBOL,
+            execution_params
+                .add_argument(RuntimeValue::I32(42))
+                .add_argument(RuntimeValue::I32(1024)),
+        );
+
+        let pointer = LittleEndian::read_u32(&env_memory.get(1024 + 8, 4).unwrap());
+        let len = LittleEndian::read_u32(&env_memory.get(1024 + 12, 4).unwrap());
+
+        let contract_code = env_memory.get(pointer, len as usize).expect("Failed to get code");
+
+        assert_eq!(raw_module, contract_code);
+    }
+
+    #[test]
+    fn call_returns_code_wasm64() {
+        let mut module = builder::module()
+            .import()
+                .module("env")
+                .field("memory")
+                .external()
+                .memory(1 as u32, Some(1 as u32))
+            .build()
+            .data()
+                .offset(elements::Opcode::I32Const(16))
+                .value(vec![0u8])
+            .build()
+            .function()
+                .signature().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(
+                        vec![
+                            elements::Opcode::End
+                        ]
+                    ))
+                    .build()
+            .build()
+            .function()
+                .signature().param().i32().build()
+                .body()
+                    .with_opcodes(elements::Opcodes::new(
+                        vec![
+                            elements::Opcode::End
+                        ]
+                    ))
+                    .build()
+            .build()
+            .export()
+                .field(CALL_SYMBOL)
+                .internal().func(0)
+            .build()
+            .export()
+                .field(CREATE_SYMBOL)
+                .internal().func(1)
+            .build()
+        .build();
+
+        let mut ctor_module = module.clone();
+        optimize(&mut module, vec![CALL_SYMBOL]).expect("Optimizer to finish without errors");
+        optimize(&mut ctor_module, vec![CREATE_SYMBOL]).expect("Optimizer to finish without errors");
+
+        let raw_module = parity_wasm::serialize(module).unwrap();
+        let ctor_module = pack_instance_with_width(raw_module.clone(), ctor_module, AddressWidth::Wasm64)
+            .expect("Packing failed");
+
+        let program = parity_wasm::DefaultProgramInstance::new().expect("Program instance failed to load");
+        let env_instance = program.module("env").expect("Wasm program to contain env module");
+        let env_memory = env_instance.memory(interpreter::ItemIndex::Internal(0)).expect("Linear memory to exist in wasm runtime");
+
+        let execution_params = interpreter::ExecutionParams::default();
+        let constructor_module = program.add_module("contract", ctor_module, None).expect("Failed to initialize module");
+
+        let _ = constructor_module.execute_export(CALL_SYMBOL, execution_params.add_argument(RuntimeValue::I32(1024)));
+
+        // Fields are 8 bytes wide under Wasm64: result_ptr at +16, result_len at +24.
+        let pointer = LittleEndian::read_u64(&env_memory.get(1024 + 16, 8).unwrap());
+        let len = LittleEndian::read_u64(&env_memory.get(1024 + 24, 8).unwrap());
+
+        let contract_code = env_memory.get(pointer as u32, len as usize).expect("Failed to get code");
+
+        assert_eq!(raw_module, contract_code);
+    }
 }
@@ -0,0 +1,162 @@
+#![crate_type = "proc-macro"]
+#![recursion_limit = "256"]
+
+extern crate proc_macro;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+
+/// Turns a plain `fn(Vec<u8>) -> Vec<u8>` contract entry point into the `call` extern the
+/// runtime actually invokes, generating the descriptor marshaling a contract would otherwise
+/// hand-roll: decode the four-field descriptor, hand the caller their input as a plain
+/// `Vec<u8>`, then commit whatever they return back through the descriptor's result slot.
+///
+/// Descriptor fields are 4 bytes wide (wasm32) by default; write `#[contract_call(wasm64)]` to
+/// generate the 8-byte-field variant for a wasm64 target instead.
+///
+/// ```ignore
+/// #[contract_call]
+/// fn call(context: Vec<u8>) -> Vec<u8> {
+///     let _ = storage::append(&context);
+///     context
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn contract_call(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let is_wasm64 = attr.to_string()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|token| token == "wasm64");
+
+    let source = item.to_string();
+    let parsed = syn::parse_item(&source).expect("#[contract_call] only applies to a fn item");
+
+    let (ident, inputs, block) = match parsed.node {
+        syn::ItemKind::Fn(ref decl, _, _, _, _, ref block) => {
+            (parsed.ident.clone(), decl.inputs.clone(), block.clone())
+        },
+        _ => panic!("#[contract_call] only applies to a fn item"),
+    };
+
+    if inputs.len() != 1 {
+        panic!("#[contract_call] entry function must take exactly one `Vec<u8>` argument");
+    }
+    let arg_pat = match inputs[0] {
+        syn::FnArg::Captured(ref pat, _) => pat.clone(),
+        _ => panic!("#[contract_call] entry function argument must be a plain binding"),
+    };
+
+    let width = if is_wasm64 {
+        quote! { __contract_call_descriptor::AddressWidth::Wasm64 }
+    } else {
+        quote! { __contract_call_descriptor::AddressWidth::Wasm32 }
+    };
+
+    let expanded = quote! {
+        mod __contract_call_descriptor {
+            use std::slice;
+
+            /// Selects the width of every pointer/length field in the call descriptor: 4 bytes
+            /// on a wasm32 target, 8 bytes on a wasm64 one. Threaded through `CallArgs` so the
+            /// same marshaling code serves either memory model.
+            #[derive(Clone, Copy)]
+            pub enum AddressWidth {
+                Wasm32,
+                Wasm64,
+            }
+
+            impl AddressWidth {
+                fn field_size(&self) -> usize {
+                    match *self {
+                        AddressWidth::Wasm32 => 4,
+                        AddressWidth::Wasm64 => 8,
+                    }
+                }
+            }
+
+            /// Safe wrapper for call context; generated by `#[contract_call]`.
+            ///
+            /// Holds the context's raw pointer/length rather than a `Box<[u8]>` on purpose: the
+            /// context points at memory owned by the calling code, never allocated by Rust's
+            /// allocator, and the result slot is not read at all, so there is nothing here for
+            /// drop glue to free if the contract body below panics while `CallArgs` is alive.
+            pub struct CallArgs {
+                context_ptr: *const u8,
+                context_len: usize,
+                width: AddressWidth,
+            }
+
+            unsafe fn read_ptr_mut(slc: &[u8], width: AddressWidth) -> *mut u8 {
+                ::std::ptr::null_mut().offset(read_uint(slc, width) as isize)
+            }
+
+            fn read_uint(slc: &[u8], width: AddressWidth) -> u64 {
+                let mut val = 0u64;
+                for i in 0..width.field_size() {
+                    val |= (slc[i] as u64) << (8 * i);
+                }
+                val
+            }
+
+            fn write_uint(dst: &mut [u8], val: u64, width: AddressWidth) {
+                for i in 0..width.field_size() {
+                    dst[i] = ((val >> (8 * i)) & 0xff) as u8;
+                }
+            }
+
+            fn write_ptr(dst: &mut [u8], ptr: *mut u8, width: AddressWidth) {
+                write_uint(dst, ptr as usize as u64, width);
+            }
+
+            impl CallArgs {
+                pub fn from_raw(ptr: *mut u8, width: AddressWidth) -> CallArgs {
+                    let field = width.field_size();
+                    let desc_slice = unsafe { slice::from_raw_parts(ptr, field * 4) };
+
+                    let context_ptr = unsafe { read_ptr_mut(&desc_slice[0..field], width) };
+                    let context_len = read_uint(&desc_slice[field..field * 2], width) as usize;
+
+                    // The result slot isn't read here; `save` writes straight into it later.
+                    // We never construct a `Box`/`Vec` over either slot in the first place, so
+                    // there's nothing for `CallArgs`'s drop glue to free even if the contract
+                    // body panics before `save` runs.
+                    CallArgs {
+                        context_ptr: context_ptr,
+                        context_len: context_len,
+                        width: width,
+                    }
+                }
+
+                pub fn context(&self) -> &[u8] {
+                    unsafe { slice::from_raw_parts(self.context_ptr, self.context_len) }
+                }
+
+                pub fn save(self, ptr: *mut u8, mut result: Vec<u8>) {
+                    let field = self.width.field_size();
+                    let dst = unsafe { slice::from_raw_parts_mut(ptr.offset(2 * field as isize), 2 * field) };
+
+                    if result.len() > 0 {
+                        write_ptr(&mut dst[0..field], result.as_mut_ptr(), self.width);
+                        write_uint(&mut dst[field..field * 2], result.len() as u64, self.width);
+                        // managed in calling code
+                        ::std::mem::forget(result);
+                    }
+                }
+            }
+        }
+
+        #[no_mangle]
+        pub fn call(descriptor: *mut u8) {
+            let ctx = __contract_call_descriptor::CallArgs::from_raw(descriptor, #width);
+            let #arg_pat = ctx.context().to_vec();
+
+            let result = (|| #block)();
+
+            ctx.save(descriptor, result);
+        }
+    };
+
+    let _ = ident;
+    expanded.parse().expect("generated call wrapper is valid Rust")
+}